@@ -1,7 +1,343 @@
 use crate::ModelProviderInfo;
-use gpui::{ClickEvent, prelude::*};
+use anyhow::Context as _;
+use editor::Editor;
+use gpui::{ClickEvent, Context, Entity, Hsla, Task, prelude::*};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use ui::{ListItem, ListItemSpacing, prelude::*};
 
+/// Number of samples retained per metric before older ones are evicted.
+pub const PROVIDER_METRICS_HISTORY_LEN: usize = 60;
+
+/// A fresh reading for a provider, as returned by its health/metrics endpoint.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProviderMetricsSample {
+    pub throughput_tps: Option<f64>,
+    pub latency_ms: Option<f64>,
+}
+
+/// Fetches live metrics for a provider. Implemented per-backend (e.g. an HTTP
+/// client hitting the provider's health endpoint) and injected so this module
+/// stays decoupled from any particular transport.
+pub trait ProviderMetricsFetcher {
+    fn fetch(&self, provider: &ModelProviderInfo) -> Task<Option<ProviderMetricsSample>>;
+}
+
+/// A capped ring buffer of recent samples for a single metric.
+#[derive(Debug, Clone, Default)]
+pub struct MetricHistory {
+    samples: VecDeque<f64>,
+}
+
+impl MetricHistory {
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == PROVIDER_METRICS_HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The most recently recorded sample, if any.
+    pub fn latest(&self) -> Option<f64> {
+        self.samples.back().copied()
+    }
+}
+
+/// Rolling history of a provider's live metrics, kept by whatever entity owns
+/// the open selector (populated via [`spawn_provider_metrics_polling`]).
+#[derive(Debug, Clone, Default)]
+pub struct ProviderMetricHistory {
+    pub throughput_tps: MetricHistory,
+    pub latency_ms: MetricHistory,
+}
+
+impl ProviderMetricHistory {
+    pub fn record(&mut self, sample: ProviderMetricsSample) {
+        if let Some(throughput) = sample.throughput_tps {
+            self.throughput_tps.push(throughput);
+        }
+        if let Some(latency) = sample.latency_ms {
+            self.latency_ms.push(latency);
+        }
+    }
+}
+
+/// Spawns a per-provider polling loop that pings `fetcher` on `interval` and
+/// hands each sample to `on_sample`. Intended to be called once per provider
+/// for each open selector; re-render is naturally debounced to the sample
+/// interval since `cx.notify()` only fires when a new sample lands.
+pub fn spawn_provider_metrics_polling<T: 'static>(
+    cx: &mut Context<T>,
+    provider: ModelProviderInfo,
+    fetcher: Rc<dyn ProviderMetricsFetcher>,
+    interval: Duration,
+    on_sample: impl Fn(&mut T, ProviderMetricsSample, &mut Context<T>) + 'static,
+) -> Task<()> {
+    cx.spawn(async move |this, cx| {
+        loop {
+            cx.background_executor().timer(interval).await;
+
+            let Some(sample) = fetcher.fetch(&provider).await else {
+                continue;
+            };
+
+            let updated = this.update(cx, |state, cx| {
+                on_sample(state, sample, cx);
+                cx.notify();
+            });
+
+            if updated.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// A column the provider list can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderSortField {
+    Speed,
+    Latency,
+    Price,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    fn toggled(self) -> Self {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
+}
+
+/// Sort/filter state for a provider selector list. Owned by whatever renders
+/// the list (e.g. the picker/modal hosting `GenericProviderListItem`s).
+#[derive(Debug, Clone, Default)]
+pub struct ProviderListState {
+    pub sort: Option<(ProviderSortField, SortDirection)>,
+    pub filter_query: String,
+}
+
+impl ProviderListState {
+    /// Clicking a header toggles direction if it's already the active column,
+    /// otherwise switches to that column ascending.
+    pub fn toggle_sort(&mut self, field: ProviderSortField) {
+        self.sort = Some(match self.sort {
+            Some((current, direction)) if current == field => (field, direction.toggled()),
+            _ => (field, SortDirection::Ascending),
+        });
+    }
+
+    pub fn matches(&self, provider: &ModelProviderInfo) -> bool {
+        if self.filter_query.trim().is_empty() {
+            return true;
+        }
+        let query = self.filter_query.trim().to_lowercase();
+        provider.display_name.to_lowercase().contains(&query)
+            || provider
+                .quantization
+                .as_ref()
+                .is_some_and(|quantization| quantization.to_lowercase().contains(&query))
+    }
+
+    /// Filters `providers` against `filter_query`, then sorts by `sort` if set.
+    pub fn apply(&self, providers: &[ModelProviderInfo]) -> Vec<ModelProviderInfo> {
+        let mut providers: Vec<_> = providers
+            .iter()
+            .filter(|provider| self.matches(provider))
+            .cloned()
+            .collect();
+
+        if let Some((field, direction)) = self.sort {
+            providers.sort_by(|a, b| compare_providers_by(a, b, field, direction));
+        }
+
+        providers
+    }
+}
+
+/// Orders `None` values last regardless of direction, so providers missing a
+/// metric sink to the bottom instead of jumping to the top on descending sorts.
+fn compare_options(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn compare_providers_by(
+    a: &ModelProviderInfo,
+    b: &ModelProviderInfo,
+    field: ProviderSortField,
+    direction: SortDirection,
+) -> Ordering {
+    // Descending negates present values rather than reversing the whole
+    // ordering, so `compare_options` still puts `None` last.
+    let sign = match direction {
+        SortDirection::Ascending => 1.0,
+        SortDirection::Descending => -1.0,
+    };
+    let key = |provider: &ModelProviderInfo| {
+        match field {
+            ProviderSortField::Speed => provider.throughput_tps,
+            ProviderSortField::Latency => provider.latency_ms,
+            ProviderSortField::Price => provider.input_price_per_million,
+        }
+        .map(|value| value * sign)
+    };
+    compare_options(key(a), key(b))
+}
+
+/// Either a full ranking of providers or a single auto-selected one, as
+/// returned by a routing policy script's `select(providers)` function.
+#[derive(Debug, Clone)]
+pub enum ProviderPolicyOutcome {
+    Ranked(Vec<usize>),
+    Selected(usize),
+}
+
+/// A user-authored Lua routing policy: given the full list of available
+/// providers it decides which one to use (or how to order them), turning
+/// provider choice into a programmable, reusable routing layer in place of
+/// manual clicking through the selector.
+pub struct ProviderRoutingPolicy {
+    source: String,
+}
+
+/// Hard ceiling on how long a routing policy script may run before it's
+/// interrupted, so a buggy or hostile script (e.g. an infinite loop) can't
+/// hang the selector.
+const ROUTING_POLICY_TIME_BUDGET: Duration = Duration::from_millis(200);
+
+impl ProviderRoutingPolicy {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+        }
+    }
+
+    /// Loads the script, calls its `select(providers)` function, and
+    /// validates the result against `providers` before returning it.
+    ///
+    /// The script runs in a sandboxed Lua state restricted to an explicit
+    /// allow-list (`table`/`string`/`math`/`utf8`) with no `io`, `os`, or
+    /// `debug` — a routing policy should only ever see the provider data
+    /// it's handed, never the filesystem or process. An interrupt also
+    /// aborts the script once it exceeds `ROUTING_POLICY_TIME_BUDGET`.
+    pub fn run(&self, providers: &[ModelProviderInfo]) -> anyhow::Result<ProviderPolicyOutcome> {
+        let allowed_libs =
+            mlua::StdLib::TABLE | mlua::StdLib::STRING | mlua::StdLib::MATH | mlua::StdLib::UTF8;
+        let lua = mlua::Lua::new_with(allowed_libs, mlua::LuaOptions::new())
+            .context("failed to initialize routing policy sandbox")?;
+
+        let deadline = Instant::now() + ROUTING_POLICY_TIME_BUDGET;
+        lua.set_interrupt(move |_lua| {
+            if Instant::now() >= deadline {
+                Err(mlua::Error::RuntimeError(
+                    "routing policy exceeded its time budget".into(),
+                ))
+            } else {
+                Ok(mlua::VmState::Continue)
+            }
+        });
+
+        lua.load(&self.source)
+            .exec()
+            .context("failed to load routing policy script")?;
+
+        let select: mlua::Function = lua
+            .globals()
+            .get("select")
+            .context("routing policy must define a `select(providers)` function")?;
+
+        let providers_table = lua
+            .create_table()
+            .context("failed to build providers table")?;
+        for (index, provider) in providers.iter().enumerate() {
+            let entry = lua.create_table()?;
+            entry.set("index", index + 1)?;
+            entry.set("display_name", provider.display_name.to_string())?;
+            entry.set("throughput_tps", provider.throughput_tps)?;
+            entry.set("latency_ms", provider.latency_ms)?;
+            entry.set("input_price_per_million", provider.input_price_per_million)?;
+            entry.set("output_price_per_million", provider.output_price_per_million)?;
+            entry.set(
+                "quantization",
+                provider.quantization.as_ref().map(|q| q.to_string()),
+            )?;
+            providers_table.set(index + 1, entry)?;
+        }
+
+        let result: mlua::Value = select
+            .call(providers_table)
+            .context("routing policy `select` raised an error")?;
+
+        Self::validate_outcome(result, providers.len())
+    }
+
+    fn validate_outcome(
+        value: mlua::Value,
+        provider_count: usize,
+    ) -> anyhow::Result<ProviderPolicyOutcome> {
+        let to_index = |lua_index: i64| -> anyhow::Result<usize> {
+            let index = lua_index
+                .checked_sub(1)
+                .and_then(|index| usize::try_from(index).ok())
+                .filter(|index| *index < provider_count)
+                .with_context(|| format!("policy referenced out-of-range index {lua_index}"))?;
+            Ok(index)
+        };
+
+        match value {
+            mlua::Value::Integer(lua_index) => {
+                Ok(ProviderPolicyOutcome::Selected(to_index(lua_index)?))
+            }
+            mlua::Value::Table(table) => {
+                let mut order = Vec::with_capacity(provider_count);
+                let mut seen = vec![false; provider_count];
+                for lua_index in table.sequence_values::<i64>() {
+                    let lua_index =
+                        lua_index.context("policy ordering must contain integers")?;
+                    let index = to_index(lua_index)?;
+                    anyhow::ensure!(
+                        !seen[index],
+                        "policy ordering must rank every provider exactly once"
+                    );
+                    seen[index] = true;
+                    order.push(index);
+                }
+                anyhow::ensure!(
+                    order.len() == provider_count,
+                    "policy ordering must rank every provider exactly once"
+                );
+                Ok(ProviderPolicyOutcome::Ranked(order))
+            }
+            other => anyhow::bail!(
+                "policy `select` must return an index or an ordered table, got {}",
+                other.type_name()
+            ),
+        }
+    }
+}
+
 fn format_price_per_million(price_per_million: f64) -> String {
     if price_per_million < 0.01 {
         format!("{:.4}", price_per_million)
@@ -12,11 +348,139 @@ fn format_price_per_million(price_per_million: f64) -> String {
     }
 }
 
+/// Renders a small bar-per-sample sparkline, normalized to the sample range.
+/// Falls back to nothing when there's fewer than two samples to compare.
+fn render_sparkline(history: &MetricHistory, cx: &App) -> Option<impl IntoElement> {
+    let samples: Vec<f64> = history.samples().collect();
+    if samples.len() < 2 {
+        return None;
+    }
+
+    let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = (max - min).max(f64::EPSILON);
+    let color = cx.theme().colors().icon_muted;
+
+    Some(
+        h_flex()
+            .items_end()
+            .gap(px(1.))
+            .h(px(10.))
+            .children(samples.into_iter().map(|sample| {
+                let normalized = ((sample - min) / range).clamp(0.0, 1.0);
+                let height = (normalized * 9.0) as f32 + 1.0;
+                div().w(px(2.)).h(px(height)).bg(color)
+            })),
+    )
+}
+
+/// Min/max bounds for each metric across the currently visible provider set,
+/// used to normalize per-cell heat-map coloring in [`GenericProviderListItem`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProviderMetricBounds {
+    throughput_tps: Option<(f64, f64)>,
+    latency_ms: Option<(f64, f64)>,
+    input_price_per_million: Option<(f64, f64)>,
+    output_price_per_million: Option<(f64, f64)>,
+}
+
+impl ProviderMetricBounds {
+    pub fn from_providers(providers: &[ModelProviderInfo]) -> Self {
+        Self {
+            throughput_tps: min_max(providers.iter().filter_map(|p| p.throughput_tps)),
+            latency_ms: min_max(providers.iter().filter_map(|p| p.latency_ms)),
+            input_price_per_million: min_max(
+                providers.iter().filter_map(|p| p.input_price_per_million),
+            ),
+            output_price_per_million: min_max(
+                providers.iter().filter_map(|p| p.output_price_per_million),
+            ),
+        }
+    }
+}
+
+fn min_max(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    values.fold(None, |bounds, value| match bounds {
+        None => Some((value, value)),
+        Some((min, max)) => Some((min.min(value), max.max(value))),
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MetricPolarity {
+    HigherIsBetter,
+    LowerIsBetter,
+}
+
+/// Normalizes `value` to `[0, 1]` within `bounds`, oriented so `1.0` is
+/// always "good" and `0.0` is always "bad" regardless of `polarity`.
+/// Returns `None` (render as neutral) when there's no value, no bounds, or
+/// every provider in view is tied — the degenerate case where a gradient
+/// would be meaningless.
+fn heat_map_normalized(
+    value: Option<f64>,
+    bounds: Option<(f64, f64)>,
+    polarity: MetricPolarity,
+) -> Option<f32> {
+    let (value, (min, max)) = (value?, bounds?);
+
+    let range = max - min;
+    if range <= f64::EPSILON {
+        return None;
+    }
+
+    let normalized = ((value - min) / range).clamp(0.0, 1.0) as f32;
+    Some(match polarity {
+        MetricPolarity::HigherIsBetter => normalized,
+        MetricPolarity::LowerIsBetter => 1.0 - normalized,
+    })
+}
+
+/// Maps `value` to a green(good)->red(bad) gradient normalized against
+/// `bounds`, using the active theme's status accents so it adapts to light
+/// and dark backgrounds. Falls back to the theme's neutral text color in the
+/// degenerate case (see [`heat_map_normalized`]).
+fn heat_map_color(
+    value: Option<f64>,
+    bounds: Option<(f64, f64)>,
+    polarity: MetricPolarity,
+    cx: &App,
+) -> Hsla {
+    match heat_map_normalized(value, bounds, polarity) {
+        Some(normalized) => lerp_hsla(
+            cx.theme().status().error,
+            cx.theme().status().success,
+            normalized,
+        ),
+        None => cx.theme().colors().text,
+    }
+}
+
+/// Linearly interpolates two HSLA colors, taking the shorter way around the
+/// hue circle so the gradient doesn't pass through unrelated hues.
+fn lerp_hsla(from: Hsla, to: Hsla, t: f32) -> Hsla {
+    let mut delta_h = to.h - from.h;
+    if delta_h > 0.5 {
+        delta_h -= 1.0;
+    } else if delta_h < -0.5 {
+        delta_h += 1.0;
+    }
+
+    Hsla {
+        h: (from.h + delta_h * t).rem_euclid(1.0),
+        s: from.s + (to.s - from.s) * t,
+        l: from.l + (to.l - from.l) * t,
+        a: from.a + (to.a - from.a) * t,
+    }
+}
+
 #[derive(IntoElement)]
 pub struct GenericProviderListItem {
     id: ElementId,
     provider: ModelProviderInfo,
     is_selected: bool,
+    metrics: Option<ProviderMetricHistory>,
+    bounds: Option<ProviderMetricBounds>,
     on_click: Option<Box<dyn Fn(&ClickEvent, &mut Window, &mut App) + 'static>>,
 }
 
@@ -26,6 +490,8 @@ impl GenericProviderListItem {
             id: id.into(),
             provider,
             is_selected: false,
+            metrics: None,
+            bounds: None,
             on_click: None,
         }
     }
@@ -35,6 +501,21 @@ impl GenericProviderListItem {
         self
     }
 
+    /// Attaches live metric history to draw sparklines from. When omitted (or
+    /// when a given metric has no samples yet) the static snapshot from
+    /// `ModelProviderInfo` is shown instead.
+    pub fn metrics(mut self, metrics: ProviderMetricHistory) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Attaches the visible set's metric bounds so each cell can be
+    /// heat-map colored relative to the other providers shown alongside it.
+    pub fn bounds(mut self, bounds: ProviderMetricBounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
     pub fn on_click(
         mut self,
         handler: impl Fn(&ClickEvent, &mut Window, &mut App) + 'static,
@@ -45,9 +526,57 @@ impl GenericProviderListItem {
 }
 
 impl RenderOnce for GenericProviderListItem {
-    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
-        let throughput = self.provider.throughput_tps.unwrap_or(0.0);
-        let latency = self.provider.latency_ms.unwrap_or(0.0);
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        // Prefer the latest live sample over the static snapshot so the
+        // number shown next to the sparkline actually moves with it.
+        let live_throughput = self
+            .metrics
+            .as_ref()
+            .and_then(|metrics| metrics.throughput_tps.latest());
+        let live_latency = self
+            .metrics
+            .as_ref()
+            .and_then(|metrics| metrics.latency_ms.latest());
+
+        let throughput_value = live_throughput.or(self.provider.throughput_tps);
+        let latency_value = live_latency.or(self.provider.latency_ms);
+
+        let throughput = throughput_value.unwrap_or(0.0);
+        let latency = latency_value.unwrap_or(0.0);
+
+        let throughput_sparkline = self
+            .metrics
+            .as_ref()
+            .and_then(|metrics| render_sparkline(&metrics.throughput_tps, cx));
+        let latency_sparkline = self
+            .metrics
+            .as_ref()
+            .and_then(|metrics| render_sparkline(&metrics.latency_ms, cx));
+
+        let throughput_color = Color::Custom(heat_map_color(
+            throughput_value,
+            self.bounds.and_then(|b| b.throughput_tps),
+            MetricPolarity::HigherIsBetter,
+            cx,
+        ));
+        let latency_color = Color::Custom(heat_map_color(
+            latency_value,
+            self.bounds.and_then(|b| b.latency_ms),
+            MetricPolarity::LowerIsBetter,
+            cx,
+        ));
+        let input_price_color = Color::Custom(heat_map_color(
+            self.provider.input_price_per_million,
+            self.bounds.and_then(|b| b.input_price_per_million),
+            MetricPolarity::LowerIsBetter,
+            cx,
+        ));
+        let output_price_color = Color::Custom(heat_map_color(
+            self.provider.output_price_per_million,
+            self.bounds.and_then(|b| b.output_price_per_million),
+            MetricPolarity::LowerIsBetter,
+            cx,
+        ));
 
         let input_price = self
             .provider
@@ -94,9 +623,13 @@ impl RenderOnce for GenericProviderListItem {
                                 v_flex().items_end().gap_0p5().child(
                                     h_flex()
                                         .gap_1()
+                                        .when_some(throughput_sparkline, |this, sparkline| {
+                                            this.child(sparkline)
+                                        })
                                         .child(
                                             Label::new(format!("{:.0}", throughput))
-                                                .size(LabelSize::XSmall),
+                                                .size(LabelSize::XSmall)
+                                                .color(throughput_color),
                                         )
                                         .child(
                                             Label::new("tok/s")
@@ -109,9 +642,13 @@ impl RenderOnce for GenericProviderListItem {
                                 v_flex().items_end().gap_0p5().child(
                                     h_flex()
                                         .gap_1()
+                                        .when_some(latency_sparkline, |this, sparkline| {
+                                            this.child(sparkline)
+                                        })
                                         .child(
                                             Label::new(format!("{:.0}ms", latency))
-                                                .size(LabelSize::XSmall),
+                                                .size(LabelSize::XSmall)
+                                                .color(latency_color),
                                         ),
                                 ),
                             )
@@ -124,7 +661,9 @@ impl RenderOnce for GenericProviderListItem {
                                             .gap_0p5()
                                             .child(Label::new("$").size(LabelSize::XSmall))
                                             .child(
-                                                Label::new(input_price).size(LabelSize::XSmall),
+                                                Label::new(input_price)
+                                                    .size(LabelSize::XSmall)
+                                                    .color(input_price_color),
                                             )
                                             .child(
                                                 Label::new("/M in")
@@ -137,7 +676,9 @@ impl RenderOnce for GenericProviderListItem {
                                             .gap_0p5()
                                             .child(Label::new("$").size(LabelSize::XSmall))
                                             .child(
-                                                Label::new(output_price).size(LabelSize::XSmall),
+                                                Label::new(output_price)
+                                                    .size(LabelSize::XSmall)
+                                                    .color(output_price_color),
                                             )
                                             .child(
                                                 Label::new("/M out")
@@ -151,8 +692,74 @@ impl RenderOnce for GenericProviderListItem {
     }
 }
 
-#[derive(IntoElement)]
-pub struct ProviderSelectorHeader;
+#[derive(IntoElement, Default)]
+pub struct ProviderSelectorHeader {
+    sort: Option<(ProviderSortField, SortDirection)>,
+    filter_editor: Option<Entity<Editor>>,
+    on_sort: Option<Rc<dyn Fn(ProviderSortField, &mut Window, &mut App) + 'static>>,
+}
+
+impl ProviderSelectorHeader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sort(mut self, sort: Option<(ProviderSortField, SortDirection)>) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn filter_editor(mut self, filter_editor: Entity<Editor>) -> Self {
+        self.filter_editor = Some(filter_editor);
+        self
+    }
+
+    pub fn on_sort(
+        mut self,
+        handler: impl Fn(ProviderSortField, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_sort = Some(Rc::new(handler));
+        self
+    }
+
+    fn render_column(
+        &self,
+        label: &'static str,
+        field: ProviderSortField,
+    ) -> impl IntoElement + use<> {
+        let active = self.sort.filter(|(sort_field, _)| *sort_field == field);
+        let on_sort = self.on_sort.clone();
+
+        h_flex()
+            .id(label)
+            .gap_0p5()
+            .when(on_sort.is_some(), |this| this.cursor_pointer())
+            .child(
+                Label::new(label)
+                    .size(LabelSize::XSmall)
+                    .color(if active.is_some() {
+                        Color::Default
+                    } else {
+                        Color::Muted
+                    }),
+            )
+            .when_some(active, |this, (_, direction)| {
+                this.child(
+                    Icon::new(match direction {
+                        SortDirection::Ascending => IconName::ChevronUp,
+                        SortDirection::Descending => IconName::ChevronDown,
+                    })
+                    .size(IconSize::XSmall)
+                    .color(Color::Default),
+                )
+            })
+            .on_click(move |_event, window, cx| {
+                if let Some(on_sort) = &on_sort {
+                    on_sort(field, window, cx);
+                }
+            })
+    }
+}
 
 impl RenderOnce for ProviderSelectorHeader {
     fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
@@ -164,6 +771,20 @@ impl RenderOnce for ProviderSelectorHeader {
                     .size(LabelSize::Small)
                     .color(Color::Muted),
             )
+            .when_some(self.filter_editor.clone(), |this, filter_editor| {
+                this.child(
+                    h_flex()
+                        .mt_1()
+                        .px_1()
+                        .gap_1()
+                        .child(
+                            Icon::new(IconName::MagnifyingGlass)
+                                .size(IconSize::XSmall)
+                                .color(Color::Muted),
+                        )
+                        .child(filter_editor),
+                )
+            })
             .child(
                 h_flex()
                     .w_full()
@@ -182,21 +803,9 @@ impl RenderOnce for ProviderSelectorHeader {
                     .child(
                         h_flex()
                             .gap_3()
-                            .child(
-                                Label::new("Speed")
-                                    .size(LabelSize::XSmall)
-                                    .color(Color::Muted),
-                            )
-                            .child(
-                                Label::new("Latency")
-                                    .size(LabelSize::XSmall)
-                                    .color(Color::Muted),
-                            )
-                            .child(
-                                Label::new("Price")
-                                    .size(LabelSize::XSmall)
-                                    .color(Color::Muted),
-                            ),
+                            .child(self.render_column("Speed", ProviderSortField::Speed))
+                            .child(self.render_column("Latency", ProviderSortField::Latency))
+                            .child(self.render_column("Price", ProviderSortField::Price)),
                     ),
             )
             .child(
@@ -223,3 +832,187 @@ impl RenderOnce for ProviderSelectorLoading {
             )
     }
 }
+
+/// Shown in place of [`ProviderSelectorLoading`] when a routing policy script
+/// fails to load, errors, or returns an invalid result.
+#[derive(IntoElement)]
+pub struct ProviderSelectorError {
+    message: SharedString,
+}
+
+impl ProviderSelectorError {
+    pub fn new(message: impl Into<SharedString>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl RenderOnce for ProviderSelectorError {
+    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        div().p_4().child(
+            v_flex()
+                .gap_1()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(
+                            Icon::new(IconName::XCircle)
+                                .size(IconSize::Small)
+                                .color(Color::Error),
+                        )
+                        .child(Label::new("Routing policy error").color(Color::Error)),
+                )
+                .child(
+                    Label::new(self.message)
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod heat_map_tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_neutral_when_all_equal() {
+        assert_eq!(
+            heat_map_normalized(Some(5.0), Some((5.0, 5.0)), MetricPolarity::HigherIsBetter),
+            None
+        );
+    }
+
+    #[test]
+    fn falls_back_to_neutral_without_bounds_or_value() {
+        assert_eq!(
+            heat_map_normalized(None, Some((0.0, 10.0)), MetricPolarity::HigherIsBetter),
+            None
+        );
+        assert_eq!(
+            heat_map_normalized(Some(5.0), None, MetricPolarity::HigherIsBetter),
+            None
+        );
+    }
+
+    #[test]
+    fn higher_is_better_maps_max_to_one() {
+        let normalized =
+            heat_map_normalized(Some(10.0), Some((0.0, 10.0)), MetricPolarity::HigherIsBetter)
+                .expect("non-degenerate bounds should normalize");
+        assert_eq!(normalized, 1.0);
+    }
+
+    #[test]
+    fn lower_is_better_inverts_normalization() {
+        let normalized =
+            heat_map_normalized(Some(10.0), Some((0.0, 10.0)), MetricPolarity::LowerIsBetter)
+                .expect("non-degenerate bounds should normalize");
+        assert_eq!(normalized, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod routing_policy_tests {
+    use super::*;
+
+    #[test]
+    fn os_and_io_are_not_available_to_scripts() {
+        let exec_attempt = ProviderRoutingPolicy::new(
+            "function select(providers) os.execute('true') return 1 end",
+        );
+        assert!(exec_attempt.run(&[]).is_err());
+
+        let io_attempt = ProviderRoutingPolicy::new(
+            "function select(providers) io.open('/dev/null') return 1 end",
+        );
+        assert!(io_attempt.run(&[]).is_err());
+    }
+
+    #[test]
+    fn accepts_a_full_permutation() {
+        let lua = mlua::Lua::new();
+        let table = lua.create_table().unwrap();
+        table.set(1, 3).unwrap();
+        table.set(2, 1).unwrap();
+        table.set(3, 2).unwrap();
+
+        let outcome = ProviderRoutingPolicy::validate_outcome(mlua::Value::Table(table), 3)
+            .expect("a valid permutation should be accepted");
+        assert!(matches!(outcome, ProviderPolicyOutcome::Ranked(order) if order == vec![2, 0, 1]));
+    }
+
+    #[test]
+    fn rejects_duplicate_indices() {
+        let lua = mlua::Lua::new();
+        let table = lua.create_table().unwrap();
+        table.set(1, 1).unwrap();
+        table.set(2, 1).unwrap();
+        table.set(3, 1).unwrap();
+
+        let result = ProviderRoutingPolicy::validate_outcome(mlua::Value::Table(table), 3);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_incomplete_ordering() {
+        let lua = mlua::Lua::new();
+        let table = lua.create_table().unwrap();
+        table.set(1, 1).unwrap();
+
+        let result = ProviderRoutingPolicy::validate_outcome(mlua::Value::Table(table), 3);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_index() {
+        let result = ProviderRoutingPolicy::validate_outcome(mlua::Value::Integer(5), 3);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn accepts_a_single_in_range_selection() {
+        let outcome = ProviderRoutingPolicy::validate_outcome(mlua::Value::Integer(2), 3)
+            .expect("an in-range 1-based index should be accepted");
+        assert!(matches!(outcome, ProviderPolicyOutcome::Selected(1)));
+    }
+}
+
+#[cfg(test)]
+mod metric_history_tests {
+    use super::*;
+
+    #[test]
+    fn caps_and_evicts_oldest_sample() {
+        let mut history = MetricHistory::default();
+        for sample in 0..PROVIDER_METRICS_HISTORY_LEN + 10 {
+            history.push(sample as f64);
+        }
+
+        let samples: Vec<f64> = history.samples().collect();
+        assert_eq!(samples.len(), PROVIDER_METRICS_HISTORY_LEN);
+        assert_eq!(samples.first().copied(), Some(10.0));
+        assert_eq!(history.latest(), Some((PROVIDER_METRICS_HISTORY_LEN + 9) as f64));
+    }
+
+    #[test]
+    fn empty_history_has_no_latest_sample() {
+        let history = MetricHistory::default();
+        assert!(history.is_empty());
+        assert_eq!(history.latest(), None);
+    }
+}
+
+#[cfg(test)]
+mod sort_filter_tests {
+    use super::*;
+
+    #[test]
+    fn none_sorts_last_regardless_of_direction() {
+        assert_eq!(compare_options(Some(1.0), None), Ordering::Less);
+        assert_eq!(compare_options(None, Some(1.0)), Ordering::Greater);
+        assert_eq!(compare_options(None, None), Ordering::Equal);
+        assert_eq!(compare_options(Some(1.0), Some(2.0)), Ordering::Less);
+    }
+}